@@ -1,22 +1,12 @@
 use anyhow::{anyhow, Result};
 use jupiter_amm_interface::AccountMap;
 use solana_sdk::{account::Account, pubkey::Pubkey};
-use stakedex_sdk_common::{
-    unstake_it_pool, unstake_it_program, STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
-    ZERO_DATA_ACC_RENT_EXEMPT_LAMPORTS,
-};
+use stakedex_sdk_common::{unstake_it_pool, unstake_it_program, RentParams};
 use unstake_interface::{
     Fee, FeeAccount, FeeEnum, Pool, PoolAccount, ProtocolFee, ProtocolFeeAccount,
 };
 use unstake_lib::{PoolBalance, ReverseFeeArgs, UnstakeFeeCalc};
 
-// TODO: STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS will change with:
-// - dynamic rent
-// - SOL minimum delegation feature
-/// The flash loan amount given out by the router program to make the slumdog stake and withdrawn stake rent-exempt.
-/// This amount is repaid by instant unstaking the slumdog stake
-pub const PREFUND_FLASH_LOAN_LAMPORTS: u64 = 2 * STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS;
-
 /// unstakeit pool account data required
 /// to give an instant unstake quote in order to power the prefund flash loan
 #[derive(Clone, Debug)]
@@ -25,6 +15,7 @@ pub struct PrefundRepayParams {
     pub incoming_stake: u64,
     pub sol_reserves_lamports: u64,
     pub protocol_fee_dest: Pubkey,
+    pub rent_params: RentParams,
 }
 
 impl PrefundRepayParams {
@@ -35,7 +26,7 @@ impl PrefundRepayParams {
         unstake_it_program::PROTOCOL_FEE_ID,
     ];
 
-    pub fn try_init(accounts_map: &AccountMap) -> Result<Self> {
+    pub fn try_init(accounts_map: &AccountMap, rent_params: RentParams) -> Result<Self> {
         let fee = extract_fee_enum(accounts_map)?;
         let incoming_stake = extract_incoming_stake(accounts_map)?;
         let sol_reserves_lamports = extract_sol_reserves_lamports(accounts_map)?;
@@ -45,10 +36,11 @@ impl PrefundRepayParams {
             incoming_stake,
             sol_reserves_lamports,
             protocol_fee_dest,
+            rent_params,
         })
     }
 
-    pub fn update(&mut self, accounts_map: &AccountMap) -> Result<()> {
+    pub fn update(&mut self, accounts_map: &AccountMap, rent_params: RentParams) -> Result<()> {
         let fee = extract_fee_enum(accounts_map)?;
         let incoming_stake = extract_incoming_stake(accounts_map)?;
         let sol_reserves_lamports = extract_sol_reserves_lamports(accounts_map)?;
@@ -58,15 +50,22 @@ impl PrefundRepayParams {
             incoming_stake,
             sol_reserves_lamports,
             protocol_fee_dest,
+            rent_params,
         };
         Ok(())
     }
 
+    /// The flash loan amount given out by the router program to make the slumdog stake and
+    /// withdrawn stake rent-exempt. This amount is repaid by instant unstaking the slumdog stake
+    pub fn prefund_flash_loan_lamports(&self) -> u64 {
+        2 * self.rent_params.stake_rent
+    }
+
     /// Computes the total lamports (including rent) that the slumdog stake account
     /// should consist of when it gets instant unstaked in order to repay the prefund flash loan
     pub fn slumdog_target_lamports(&self) -> Result<u64> {
-        let lamports_required = PREFUND_FLASH_LOAN_LAMPORTS;
-        if self.sol_reserves_lamports < lamports_required + ZERO_DATA_ACC_RENT_EXEMPT_LAMPORTS {
+        let lamports_required = self.prefund_flash_loan_lamports();
+        if self.sol_reserves_lamports < lamports_required + self.rent_params.zero_data_rent {
             return Err(anyhow!("Not enough liquidity for slumdog instant unstake"));
         }
         self.fee
@@ -89,10 +88,10 @@ impl PrefundRepayParams {
     ///
     /// The stake account instant unstaked to repay the flash loan will comprise
     /// - return value staked lamports
-    /// - STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS unstaked lamports
+    /// - rent_params.stake_rent unstaked lamports
     pub fn prefund_split_lamports(&self) -> Result<u64> {
         let slumdog_target_lamports = self.slumdog_target_lamports()?;
-        Ok(slumdog_target_lamports.saturating_sub(STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS))
+        Ok(slumdog_target_lamports.saturating_sub(self.rent_params.stake_rent))
     }
 }
 
@@ -3,12 +3,11 @@ use lazy_static::lazy_static;
 use solana_program::{borsh::try_from_slice_unchecked, pubkey::Pubkey, stake_history::Epoch};
 use spl_stake_pool::{
     error::StakePoolError,
-    state::{StakePool, StakeStatus, ValidatorList},
+    state::{Fee, FutureEpoch, StakePool, StakeStatus, ValidatorList, ValidatorStakeInfo},
 };
 use stakedex_sdk_common::{
     cogent_stake_pool, daopool_stake_pool, jito_stake_pool, jpool_stake_pool, laine_stake_pool,
-    mrgn_stake_pool, risklol_stake_pool, solblaze_stake_pool, WithdrawStakeQuote,
-    STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS,
+    mrgn_stake_pool, risklol_stake_pool, solblaze_stake_pool, RentParams, WithdrawStakeQuote,
 };
 use std::collections::HashMap;
 
@@ -38,6 +37,15 @@ pub struct SplStakePoolStakedex {
     pub stake_pool: StakePool,
     pub validator_list: ValidatorList,
     pub curr_epoch: Epoch,
+    pub reserve_stake_lamports: Option<u64>,
+    pub rent_params: RentParams,
+    /// `false` if the pool has set a `stake_deposit_authority` other than the program-derived
+    /// default, meaning stake deposits require a signature the router doesn't hold. Set in
+    /// `update()`; `DepositStake::can_accept_stake_deposits` does not read it.
+    pub accepts_stake_deposits: bool,
+    /// `false` if the pool has set a `sol_deposit_authority` that must co-sign SOL deposits.
+    /// Set in `update()`; `DepositSol::can_accept_sol_deposits` does not read it.
+    pub accepts_sol_deposits: bool,
 }
 
 impl SplStakePoolStakedex {
@@ -55,24 +63,40 @@ impl SplStakePoolStakedex {
         self.stake_pool.last_update_epoch >= self.curr_epoch
     }
 
-    fn get_quote_for_validator_copied(
+    /// The `stake_withdrawal_fee` that will actually be charged once this withdrawal lands.
+    ///
+    /// If the pool hasn't been cranked `Update`d this epoch yet, the crank that runs before our
+    /// withdrawal executes will swap `next_stake_withdrawal_fee` in as the active fee, so we must
+    /// quote against that fee rather than the stale current one. Only `FutureEpoch::One` takes
+    /// effect on the very next `Update`; `TwoEpochsFromNow` is just decremented to `One` that
+    /// round and doesn't apply for another epoch after that, so it must not be swapped in early.
+    fn effective_stake_withdrawal_fee(&self) -> Fee {
+        if self.is_updated_this_epoch() {
+            return self.stake_pool.stake_withdrawal_fee;
+        }
+        match self.stake_pool.next_stake_withdrawal_fee {
+            FutureEpoch::One(fee) => fee,
+            FutureEpoch::None | FutureEpoch::TwoEpochsFromNow(_) => {
+                self.stake_pool.stake_withdrawal_fee
+            }
+        }
+    }
+
+    /// Copied pool-tokens -> lamports fee math shared by all withdraw sources (active, transient,
+    /// reserve). Returns `(withdraw_lamports, pool_tokens_fee)`.
+    ///
+    /// Copied from:
+    /// https://github.com/solana-labs/solana-program-library/blob/58c1226a513d3d8bb2de8ec67586a679be7fd2d4/stake-pool/program/src/processor.rs#L2297
+    fn calc_withdraw_lamports_and_fee(
         &self,
-        validator_index: usize,
         withdraw_amount: u64,
-    ) -> Result<WithdrawStakeQuote, StakePoolError> {
-        let validator_list_entry = self.validator_list.validators.get(validator_index).unwrap();
-        // only handle withdrawal from active stake accounts for simplicity.
-        // Likely other stake pools can't accept non active stake anyway
-        if validator_list_entry.status != StakeStatus::Active {
-            return Err(StakePoolError::InvalidState);
-        }
+    ) -> Result<(u64, u64), StakePoolError> {
         let stake_pool = &self.stake_pool;
         let pool_tokens = withdraw_amount;
 
-        // Copied from:
-        // https://github.com/solana-labs/solana-program-library/blob/58c1226a513d3d8bb2de8ec67586a679be7fd2d4/stake-pool/program/src/processor.rs#L2297
-        let pool_tokens_fee = stake_pool
-            .calc_pool_tokens_stake_withdrawal_fee(pool_tokens)
+        let pool_tokens_fee = self
+            .effective_stake_withdrawal_fee()
+            .apply(pool_tokens)
             .ok_or(StakePoolError::CalculationFailure)?;
         let pool_tokens_burnt = pool_tokens
             .checked_sub(pool_tokens_fee)
@@ -85,7 +109,20 @@ impl SplStakePoolStakedex {
         if withdraw_lamports == 0 {
             return Err(StakePoolError::WithdrawalTooSmall);
         }
-        // end copy
+        Ok((withdraw_lamports, pool_tokens_fee))
+    }
+
+    fn get_quote_for_validator_copied(
+        &self,
+        validator_index: usize,
+        withdraw_amount: u64,
+    ) -> Result<WithdrawStakeQuote, StakePoolError> {
+        let validator_list_entry = self.validator_list.validators.get(validator_index).unwrap();
+        if validator_list_entry.status != StakeStatus::Active {
+            return Err(StakePoolError::InvalidState);
+        }
+        let (withdraw_lamports, pool_tokens_fee) =
+            self.calc_withdraw_lamports_and_fee(withdraw_amount)?;
 
         // according to https://github.com/solana-labs/solana-program-library/blob/58c1226a513d3d8bb2de8ec67586a679be7fd2d4/stake-pool/program/src/state.rs#L536C1-L542
         // `active_stake_lamports` = delegation.stake - MIN_ACTIVE_STAKE_LAMPORTS.
@@ -94,8 +131,11 @@ impl SplStakePoolStakedex {
             return Err(StakePoolError::InvalidState);
         }
         let lamports_staked = withdraw_lamports
-            .checked_sub(STAKE_ACCOUNT_RENT_EXEMPT_LAMPORTS)
+            .checked_sub(self.rent_params.stake_rent)
             .ok_or(StakePoolError::CalculationFailure)?;
+        if lamports_staked < self.rent_params.min_delegation {
+            return Err(StakePoolError::WithdrawalTooSmall);
+        }
         Ok(WithdrawStakeQuote {
             lamports_out: withdraw_lamports,
             lamports_staked,
@@ -103,11 +143,189 @@ impl SplStakePoolStakedex {
             voter: validator_list_entry.vote_account_address,
         })
     }
+
+    /// Same as [`Self::get_quote_for_validator_copied`], but for a validator currently
+    /// deactivating its stake (`StakeStatus::DeactivatingTransient`). The stake-pool program
+    /// allows withdrawing from transient stake so long as it doesn't drain below zero.
+    fn get_quote_for_transient_copied(
+        &self,
+        validator_index: usize,
+        withdraw_amount: u64,
+    ) -> Result<WithdrawStakeQuote, StakePoolError> {
+        let validator_list_entry = self.validator_list.validators.get(validator_index).unwrap();
+        if validator_list_entry.status != StakeStatus::DeactivatingTransient {
+            return Err(StakePoolError::InvalidState);
+        }
+        let (withdraw_lamports, pool_tokens_fee) =
+            self.calc_withdraw_lamports_and_fee(withdraw_amount)?;
+        if withdraw_lamports > validator_list_entry.transient_stake_lamports {
+            return Err(StakePoolError::InvalidState);
+        }
+        let lamports_staked = withdraw_lamports
+            .checked_sub(self.rent_params.stake_rent)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        if lamports_staked < self.rent_params.min_delegation {
+            return Err(StakePoolError::WithdrawalTooSmall);
+        }
+        Ok(WithdrawStakeQuote {
+            lamports_out: withdraw_lamports,
+            lamports_staked,
+            fee_amount: pool_tokens_fee,
+            voter: validator_list_entry.vote_account_address,
+        })
+    }
+
+    /// Withdraws straight from the pool's reserve stake account. [`Self::find_withdraw_stake_quote`]
+    /// only considers this source once [`Self::reserve_withdraw_eligible`] holds, i.e. every
+    /// validator has zero active and zero transient stake. The resulting stake account isn't delegated to any
+    /// validator, so `voter` is left as the default pubkey; callers must treat a reserve quote as
+    /// undelegated stake rather than a stake account belonging to `voter`.
+    fn get_quote_for_reserve_copied(
+        &self,
+        withdraw_amount: u64,
+    ) -> Result<WithdrawStakeQuote, StakePoolError> {
+        let reserve_stake_lamports = self
+            .reserve_stake_lamports
+            .ok_or(StakePoolError::InvalidState)?;
+        let (withdraw_lamports, pool_tokens_fee) =
+            self.calc_withdraw_lamports_and_fee(withdraw_amount)?;
+        // the reserve stake account itself must stay rent-exempt after the split
+        let withdrawable_reserve_lamports = reserve_stake_lamports
+            .checked_sub(self.rent_params.stake_rent)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        if withdraw_lamports > withdrawable_reserve_lamports {
+            return Err(StakePoolError::InvalidState);
+        }
+        let lamports_staked = withdraw_lamports
+            .checked_sub(self.rent_params.stake_rent)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        Ok(WithdrawStakeQuote {
+            lamports_out: withdraw_lamports,
+            lamports_staked,
+            fee_amount: pool_tokens_fee,
+            voter: Pubkey::default(),
+        })
+    }
+
+    /// Returns true if `validator_list_entry`'s active stake may be withdrawn given the pool's
+    /// `preferred_withdraw_validator_vote_address`. The stake-pool program requires withdrawals
+    /// to drain the preferred validator first; others only become eligible once the preferred
+    /// validator has no active stake left to give.
+    fn validator_eligible_for_active_withdraw(
+        &self,
+        validator_list_entry: &ValidatorStakeInfo,
+    ) -> bool {
+        match self.stake_pool.preferred_withdraw_validator_vote_address {
+            None => true,
+            Some(preferred) => {
+                validator_list_entry.vote_account_address == preferred
+                    || self
+                        .validator_list
+                        .validators
+                        .iter()
+                        .find(|v| v.vote_account_address == preferred)
+                        .map_or(true, |v| v.active_stake_lamports == 0)
+            }
+        }
+    }
+
+    /// Returns true if the reserve may be withdrawn from. The stake-pool program only allows
+    /// withdrawing from the reserve once every validator in the list has been drawn down to zero
+    /// active stake *and* its transient stake (if any) has fully settled back to the reserve;
+    /// otherwise the withdraw instruction rejects it. A validator mid-`DeactivatingTransient`
+    /// still has `transient_stake_lamports > 0` even though `active_stake_lamports` already
+    /// reads zero, so both must be checked.
+    fn reserve_withdraw_eligible(&self) -> bool {
+        self.validator_list
+            .validators
+            .iter()
+            .all(|v| v.active_stake_lamports == 0 && v.transient_stake_lamports == 0)
+    }
+
+    /// Scans the validator list (active and transient stake) plus the reserve for the withdraw
+    /// source that yields the largest `lamports_out` for `withdraw_amount`, instead of quoting a
+    /// single validator by index as [`Self::get_quote_for_validator_copied`] does. This crate's
+    /// `WithdrawStake` trait impl does not call this method.
+    ///
+    /// Known limitation, not covered by the withdrawal-fee quoting above: near an epoch boundary,
+    /// the crank that runs before our withdrawal also recomputes validator/reserve lamport
+    /// balances (e.g. settling transient stake that finished activating/deactivating), so
+    /// `active_stake_lamports`/`transient_stake_lamports`/`reserve_stake_lamports` here can still
+    /// be stale relative to what the crank is about to write. Modeling that recompute is a
+    /// separate, currently unscheduled piece of follow-up work.
+    pub fn get_best_withdraw_stake_quote(
+        &self,
+        withdraw_amount: u64,
+    ) -> Result<WithdrawStakeQuote, StakePoolError> {
+        self.find_withdraw_stake_quote(withdraw_amount, |_| true, true)
+    }
+
+    /// Like [`Self::get_best_withdraw_stake_quote`], but only considers the validator whose vote
+    /// account is `vote`. Lets a router withdraw from and deposit back into the same validator in
+    /// a single hop.
+    pub fn find_validator_withdraw_quote(
+        &self,
+        vote: &Pubkey,
+        withdraw_amount: u64,
+    ) -> Result<WithdrawStakeQuote, StakePoolError> {
+        self.find_withdraw_stake_quote(
+            withdraw_amount,
+            |validator_list_entry| validator_list_entry.vote_account_address == *vote,
+            false,
+        )
+    }
+
+    /// Walks `self.validator_list.validators`, evaluating `pred` on each entry whose status is
+    /// `Active` or `DeactivatingTransient`, then optionally considers the reserve, returning the
+    /// quote with the greatest `lamports_out` among all eligible sources.
+    fn find_withdraw_stake_quote(
+        &self,
+        withdraw_amount: u64,
+        pred: impl Fn(&ValidatorStakeInfo) -> bool,
+        include_reserve: bool,
+    ) -> Result<WithdrawStakeQuote, StakePoolError> {
+        let mut best: Option<WithdrawStakeQuote> = None;
+        let mut consider = |quote: WithdrawStakeQuote| {
+            if best.as_ref().map_or(true, |b| quote.lamports_out > b.lamports_out) {
+                best = Some(quote);
+            }
+        };
+        for (index, validator_list_entry) in self.validator_list.validators.iter().enumerate() {
+            if !pred(validator_list_entry) {
+                continue;
+            }
+            match validator_list_entry.status {
+                StakeStatus::Active => {
+                    if !self.validator_eligible_for_active_withdraw(validator_list_entry) {
+                        continue;
+                    }
+                    if let Ok(quote) = self.get_quote_for_validator_copied(index, withdraw_amount)
+                    {
+                        consider(quote);
+                    }
+                }
+                StakeStatus::DeactivatingTransient => {
+                    if let Ok(quote) = self.get_quote_for_transient_copied(index, withdraw_amount)
+                    {
+                        consider(quote);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if include_reserve && self.reserve_withdraw_eligible() {
+            if let Ok(quote) = self.get_quote_for_reserve_copied(withdraw_amount) {
+                consider(quote);
+            }
+        }
+        best.ok_or(StakePoolError::InvalidState)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use spl_stake_pool::state::FutureEpoch;
     use stakedex_sdk_common::DepositSolWrapper;
 
     #[test]
@@ -116,4 +334,170 @@ mod tests {
         // impls Amm
         let _sp = DepositSolWrapper(SplStakePoolStakedex::default());
     }
+
+    fn validator_with_active_stake(vote: Pubkey, active_stake_lamports: u64) -> ValidatorStakeInfo {
+        ValidatorStakeInfo {
+            vote_account_address: vote,
+            active_stake_lamports,
+            ..Default::default()
+        }
+    }
+
+    fn validator_with_transient_stake(vote: Pubkey, transient_stake_lamports: u64) -> ValidatorStakeInfo {
+        ValidatorStakeInfo {
+            vote_account_address: vote,
+            status: StakeStatus::DeactivatingTransient,
+            transient_stake_lamports,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn preferred_validator_is_eligible_even_while_others_still_hold_active_stake() {
+        let preferred = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mut sp = SplStakePoolStakedex::default();
+        sp.stake_pool.preferred_withdraw_validator_vote_address = Some(preferred);
+        sp.validator_list.validators = vec![
+            validator_with_active_stake(preferred, 1_000_000),
+            validator_with_active_stake(other, 1_000_000),
+        ];
+
+        assert!(sp.validator_eligible_for_active_withdraw(&sp.validator_list.validators[0]));
+        assert!(!sp.validator_eligible_for_active_withdraw(&sp.validator_list.validators[1]));
+    }
+
+    #[test]
+    fn other_validators_become_eligible_once_preferred_is_drained() {
+        let preferred = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mut sp = SplStakePoolStakedex::default();
+        sp.stake_pool.preferred_withdraw_validator_vote_address = Some(preferred);
+        sp.validator_list.validators = vec![
+            validator_with_active_stake(preferred, 0),
+            validator_with_active_stake(other, 1_000_000),
+        ];
+
+        assert!(sp.validator_eligible_for_active_withdraw(&sp.validator_list.validators[1]));
+    }
+
+    #[test]
+    fn no_preferred_validator_means_all_are_eligible() {
+        let mut sp = SplStakePoolStakedex::default();
+        sp.validator_list.validators = vec![validator_with_active_stake(
+            Pubkey::new_unique(),
+            1_000_000,
+        )];
+
+        assert!(sp.validator_eligible_for_active_withdraw(&sp.validator_list.validators[0]));
+    }
+
+    #[test]
+    fn reserve_ineligible_while_any_validator_still_holds_active_stake() {
+        let mut sp = SplStakePoolStakedex::default();
+        sp.validator_list.validators = vec![
+            validator_with_active_stake(Pubkey::new_unique(), 0),
+            validator_with_active_stake(Pubkey::new_unique(), 1),
+        ];
+
+        assert!(!sp.reserve_withdraw_eligible());
+    }
+
+    #[test]
+    fn reserve_eligible_once_every_validator_is_drained() {
+        let mut sp = SplStakePoolStakedex::default();
+        sp.validator_list.validators = vec![
+            validator_with_active_stake(Pubkey::new_unique(), 0),
+            validator_with_active_stake(Pubkey::new_unique(), 0),
+        ];
+
+        assert!(sp.reserve_withdraw_eligible());
+    }
+
+    #[test]
+    fn reserve_eligible_with_empty_validator_list() {
+        let sp = SplStakePoolStakedex::default();
+
+        assert!(sp.reserve_withdraw_eligible());
+    }
+
+    #[test]
+    fn reserve_ineligible_while_transient_stake_still_settling() {
+        let mut sp = SplStakePoolStakedex::default();
+        sp.validator_list.validators = vec![
+            validator_with_active_stake(Pubkey::new_unique(), 0),
+            validator_with_transient_stake(Pubkey::new_unique(), 1),
+        ];
+
+        assert!(!sp.reserve_withdraw_eligible());
+    }
+
+    #[test]
+    fn effective_fee_is_current_fee_when_updated_this_epoch() {
+        let current_fee = Fee {
+            numerator: 1,
+            denominator: 1000,
+        };
+        let mut sp = SplStakePoolStakedex::default();
+        sp.curr_epoch = 5;
+        sp.stake_pool.last_update_epoch = 5;
+        sp.stake_pool.stake_withdrawal_fee = current_fee;
+        sp.stake_pool.next_stake_withdrawal_fee = FutureEpoch::One(Fee {
+            numerator: 2,
+            denominator: 1000,
+        });
+
+        assert_eq!(sp.effective_stake_withdrawal_fee(), current_fee);
+    }
+
+    #[test]
+    fn effective_fee_swaps_in_next_fee_when_not_yet_updated_this_epoch() {
+        let next_fee = Fee {
+            numerator: 2,
+            denominator: 1000,
+        };
+        let mut sp = SplStakePoolStakedex::default();
+        sp.curr_epoch = 6;
+        sp.stake_pool.last_update_epoch = 5;
+        sp.stake_pool.stake_withdrawal_fee = Fee {
+            numerator: 1,
+            denominator: 1000,
+        };
+        sp.stake_pool.next_stake_withdrawal_fee = FutureEpoch::One(next_fee);
+
+        assert_eq!(sp.effective_stake_withdrawal_fee(), next_fee);
+    }
+
+    #[test]
+    fn effective_fee_falls_back_to_current_fee_when_no_fee_change_scheduled() {
+        let current_fee = Fee {
+            numerator: 1,
+            denominator: 1000,
+        };
+        let mut sp = SplStakePoolStakedex::default();
+        sp.curr_epoch = 6;
+        sp.stake_pool.last_update_epoch = 5;
+        sp.stake_pool.stake_withdrawal_fee = current_fee;
+
+        assert_eq!(sp.effective_stake_withdrawal_fee(), current_fee);
+    }
+
+    #[test]
+    fn effective_fee_does_not_swap_in_a_fee_still_two_epochs_away() {
+        let current_fee = Fee {
+            numerator: 1,
+            denominator: 1000,
+        };
+        let mut sp = SplStakePoolStakedex::default();
+        sp.curr_epoch = 6;
+        sp.stake_pool.last_update_epoch = 5;
+        sp.stake_pool.stake_withdrawal_fee = current_fee;
+        sp.stake_pool.next_stake_withdrawal_fee = FutureEpoch::TwoEpochsFromNow(Fee {
+            numerator: 2,
+            denominator: 1000,
+        });
+
+        // the upcoming crank only decrements TwoEpochsFromNow to One; it doesn't apply it yet
+        assert_eq!(sp.effective_stake_withdrawal_fee(), current_fee);
+    }
 }
@@ -2,12 +2,33 @@ use std::num::NonZeroU64;
 
 use anyhow::Result;
 use jupiter_amm_interface::{AccountMap, AmmContext, KeyedAccount};
-use solana_program::pubkey::Pubkey;
+use solana_program::{pubkey::Pubkey, rent::Rent, stake::state::StakeStateV2, sysvar};
 use spl_stake_pool::error::StakePoolError;
-use stakedex_sdk_common::{account_missing_err, BaseStakePoolAmm, InitFromKeyedAccount};
+use stakedex_sdk_common::{account_missing_err, BaseStakePoolAmm, InitFromKeyedAccount, RentParams};
 
 use crate::{SplStakePoolStakedex, SplStakePoolStakedexWithWithdrawSol};
 
+/// Reads `RentParams` off the `Rent` sysvar account.
+///
+/// `stake_rent`/`zero_data_rent` are genuinely dynamic and computed from the fetched `Rent`
+/// sysvar. `min_delegation` is NOT: the stake program only exposes it by simulating the
+/// `get_minimum_delegation` instruction, which this account-fetch-based `update()` has no way to
+/// do. Until the SDK threads through a simulation RPC call, this falls back to the current
+/// mainnet value of 1 SOL and will go stale if that value ever changes again.
+fn extract_rent_params(accounts_map: &AccountMap) -> Result<RentParams> {
+    let rent_data = accounts_map
+        .get(&sysvar::rent::ID)
+        .ok_or_else(|| account_missing_err(&sysvar::rent::ID))?
+        .data
+        .as_ref();
+    let rent: Rent = bincode::deserialize(rent_data)?;
+    Ok(RentParams {
+        stake_rent: rent.minimum_balance(std::mem::size_of::<StakeStateV2>()),
+        zero_data_rent: rent.minimum_balance(0),
+        min_delegation: solana_program::native_token::LAMPORTS_PER_SOL,
+    })
+}
+
 impl InitFromKeyedAccount for SplStakePoolStakedex {
     /// Initialize from stake pool main account
     fn from_keyed_account(
@@ -64,7 +85,12 @@ impl BaseStakePoolAmm for SplStakePoolStakedex {
 
     #[inline]
     fn get_accounts_to_update(&self) -> Vec<Pubkey> {
-        let mut res = Vec::from([self.stake_pool_addr, self.stake_pool.validator_list]);
+        let mut res = Vec::from([
+            self.stake_pool_addr,
+            self.stake_pool.validator_list,
+            self.stake_pool.reserve_stake,
+            sysvar::rent::ID,
+        ]);
         if self.is_sol_deposit_capped() || self.is_stake_deposit_capped() {
             res.push(self.spl_deposit_cap_guard_program_address);
         }
@@ -84,6 +110,19 @@ impl BaseStakePoolAmm for SplStakePoolStakedex {
             .data
             .as_ref();
         self.update_validator_list(validator_list_data)?;
+        let reserve_stake_acc = accounts_map
+            .get(&self.stake_pool.reserve_stake)
+            .ok_or_else(|| account_missing_err(&self.stake_pool.reserve_stake))?;
+        self.reserve_stake_lamports = Some(reserve_stake_acc.lamports);
+        self.rent_params = extract_rent_params(accounts_map)?;
+        let (default_stake_deposit_authority, _) =
+            spl_stake_pool::find_deposit_authority_program_address(
+                &self.program_id(),
+                &self.stake_pool_addr,
+            );
+        self.accepts_stake_deposits =
+            self.stake_pool.stake_deposit_authority == default_stake_deposit_authority;
+        self.accepts_sol_deposits = self.stake_pool.sol_deposit_authority.is_none();
         if self.is_sol_deposit_capped() || self.is_stake_deposit_capped() {
             let deposit_cap_data = accounts_map
                 .get(&self.spl_deposit_cap_guard_program_address)
@@ -129,9 +168,8 @@ impl BaseStakePoolAmm for SplStakePoolStakedexWithWithdrawSol {
 
     #[inline]
     fn get_accounts_to_update(&self) -> Vec<Pubkey> {
-        let mut res = self.inner.get_accounts_to_update();
-        res.push(self.inner.stake_pool.reserve_stake);
-        res
+        // self.inner.get_accounts_to_update() already includes stake_pool.reserve_stake
+        self.inner.get_accounts_to_update()
     }
 
     fn update(&mut self, account_map: &AccountMap) -> Result<()> {
@@ -0,0 +1,14 @@
+use stakedex_sdk_common::RentParams;
+use unstake_interface::{Fee, Pool, ProtocolFee};
+
+mod stakedex_traits;
+pub use stakedex_traits::*;
+
+#[derive(Clone, Default)]
+pub struct UnstakeItStakedex {
+    pub fee: Fee,
+    pub pool: Pool,
+    pub protocol_fee: ProtocolFee,
+    pub sol_reserves_lamports: u64,
+    pub rent_params: RentParams,
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use jupiter_amm_interface::{AccountMap, AmmContext, KeyedAccount};
+use solana_program::{pubkey::Pubkey, rent::Rent, stake::state::StakeStateV2, sysvar};
+use stakedex_sdk_common::{
+    account_missing_err, unstake_it_pool, unstake_it_program, BaseStakePoolAmm,
+    InitFromKeyedAccount, RentParams,
+};
+use unstake_interface::{Fee, FeeAccount, Pool, PoolAccount, ProtocolFee, ProtocolFeeAccount};
+
+use crate::UnstakeItStakedex;
+
+/// Reads `RentParams` off the `Rent` sysvar account. Mirrors
+/// `spl_stake_pool::stakedex_traits::base::extract_rent_params`: `stake_rent`/`zero_data_rent`
+/// are genuinely dynamic; `min_delegation` is a hard-coded fallback until the SDK can simulate
+/// the stake program's `get_minimum_delegation` instruction.
+fn extract_rent_params(accounts_map: &AccountMap) -> Result<RentParams> {
+    let rent_data = accounts_map
+        .get(&sysvar::rent::ID)
+        .ok_or_else(|| account_missing_err(&sysvar::rent::ID))?
+        .data
+        .as_ref();
+    let rent: Rent = bincode::deserialize(rent_data)?;
+    Ok(RentParams {
+        stake_rent: rent.minimum_balance(std::mem::size_of::<StakeStateV2>()),
+        zero_data_rent: rent.minimum_balance(0),
+        min_delegation: solana_program::native_token::LAMPORTS_PER_SOL,
+    })
+}
+
+impl InitFromKeyedAccount for UnstakeItStakedex {
+    #[inline]
+    fn from_keyed_account(_keyed_account: &KeyedAccount, _amm_context: &AmmContext) -> Result<Self> {
+        Ok(Self::default())
+    }
+}
+
+impl BaseStakePoolAmm for UnstakeItStakedex {
+    #[inline]
+    fn program_id(&self) -> Pubkey {
+        unstake_it_program::ID
+    }
+
+    #[inline]
+    fn stake_pool_label(&self) -> &str {
+        "unstake.it"
+    }
+
+    #[inline]
+    fn main_state_key(&self) -> Pubkey {
+        unstake_it_pool::ID
+    }
+
+    #[inline]
+    fn staked_sol_mint(&self) -> Pubkey {
+        spl_token::native_mint::ID
+    }
+
+    #[inline]
+    fn get_accounts_to_update(&self) -> Vec<Pubkey> {
+        Vec::from([
+            unstake_it_pool::ID,
+            unstake_it_program::FEE_ID,
+            unstake_it_program::SOL_RESERVES_ID,
+            unstake_it_program::PROTOCOL_FEE_ID,
+            sysvar::rent::ID,
+        ])
+    }
+
+    fn update(&mut self, accounts_map: &AccountMap) -> Result<()> {
+        let fee_acc = accounts_map
+            .get(&unstake_it_program::FEE_ID)
+            .ok_or_else(|| account_missing_err(&unstake_it_program::FEE_ID))?;
+        let FeeAccount(fee): FeeAccount = FeeAccount::deserialize(&fee_acc.data)?;
+        self.fee = fee;
+
+        let pool_acc = accounts_map
+            .get(&unstake_it_pool::ID)
+            .ok_or_else(|| account_missing_err(&unstake_it_pool::ID))?;
+        let PoolAccount(pool): PoolAccount = PoolAccount::deserialize(&pool_acc.data)?;
+        self.pool = pool;
+
+        let protocol_fee_acc = accounts_map
+            .get(&unstake_it_program::PROTOCOL_FEE_ID)
+            .ok_or_else(|| account_missing_err(&unstake_it_program::PROTOCOL_FEE_ID))?;
+        let ProtocolFeeAccount(protocol_fee): ProtocolFeeAccount =
+            ProtocolFeeAccount::deserialize(&protocol_fee_acc.data)?;
+        self.protocol_fee = protocol_fee;
+
+        let sol_reserves_acc = accounts_map
+            .get(&unstake_it_program::SOL_RESERVES_ID)
+            .ok_or_else(|| account_missing_err(&unstake_it_program::SOL_RESERVES_ID))?;
+        self.sol_reserves_lamports = sol_reserves_acc.lamports;
+
+        self.rent_params = extract_rent_params(accounts_map)?;
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ use stakedex_deposit_stake_interface::{
 };
 use stakedex_sdk_common::{
     unstake_it_pool, unstake_it_program, DepositStake, DepositStakeInfo, DepositStakeQuote,
-    WithdrawStakeQuote, ZERO_DATA_ACC_RENT_EXEMPT_LAMPORTS,
+    WithdrawStakeQuote,
 };
 use std::cmp::Ordering;
 
@@ -39,7 +39,7 @@ impl DepositStake for UnstakeItStakedex {
             Ordering::Greater => return DepositStakeQuote::default(),
             Ordering::Less => {
                 // cannot leave reserves below rent-exempt min
-                if self.sol_reserves_lamports - tokens_out < ZERO_DATA_ACC_RENT_EXEMPT_LAMPORTS {
+                if self.sol_reserves_lamports - tokens_out < self.rent_params.zero_data_rent {
                     return DepositStakeQuote::default();
                 }
             }